@@ -0,0 +1,180 @@
+use tokio::sync::mpsc::Sender;
+
+use crate::mqttbytes::topic::{valid_filter, valid_topic, Filter, InvalidFilterError, InvalidTopicError};
+use crate::mqttbytes::QoS;
+use crate::{EventLoop, MqttOptions, Publish, Request, Subscribe, SubscribeFilter};
+
+/// Errors returned when a publish or subscribe request can't be sent.
+#[derive(Debug)]
+pub enum ClientError {
+    /// `publish()` was called with a topic that fails `valid_topic`.
+    InvalidTopic(InvalidTopicError),
+    /// `subscribe()`/`subscribe_many()` was called with a filter that fails
+    /// `valid_filter`.
+    InvalidFilter(InvalidFilterError),
+    /// The eventloop has shut down and can no longer accept requests.
+    EventloopClosed,
+}
+
+impl From<InvalidTopicError> for ClientError {
+    fn from(err: InvalidTopicError) -> Self {
+        ClientError::InvalidTopic(err)
+    }
+}
+
+impl From<InvalidFilterError> for ClientError {
+    fn from(err: InvalidFilterError) -> Self {
+        ClientError::InvalidFilter(err)
+    }
+}
+
+/// Asynchronous handle used to publish and subscribe from anywhere in the
+/// program while the paired [`EventLoop`] drives the actual connection.
+#[derive(Clone, Debug)]
+pub struct AsyncClient {
+    request_tx: Sender<Request>,
+}
+
+impl AsyncClient {
+    /// Creates a client and the eventloop that drives its connection.
+    pub fn new(options: MqttOptions, cap: usize) -> (AsyncClient, EventLoop) {
+        let eventloop = EventLoop::new(options, cap);
+        let request_tx = eventloop.handle();
+        (AsyncClient { request_tx }, eventloop)
+    }
+
+    async fn send(&self, request: Request) -> Result<(), ClientError> {
+        self.request_tx
+            .send(request)
+            .await
+            .map_err(|_| ClientError::EventloopClosed)
+    }
+
+    /// Publishes to `topic`, validating it first so an invalid topic comes
+    /// back as `ClientError::InvalidTopic` instead of being silently dropped
+    /// further down the pipeline.
+    pub async fn publish<S, P>(
+        &self,
+        topic: S,
+        qos: QoS,
+        retain: bool,
+        payload: P,
+    ) -> Result<(), ClientError>
+    where
+        S: Into<String>,
+        P: Into<Vec<u8>>,
+    {
+        let topic = topic.into();
+        valid_topic(&topic)?;
+
+        let mut publish = Publish::new(topic, qos, payload);
+        publish.retain = retain;
+        self.send(Request::Publish(publish)).await
+    }
+
+    /// Subscribes to a single raw filter pattern, validating it first so the
+    /// caller gets an actionable `ClientError::InvalidFilter` instead of
+    /// whatever the eventloop would otherwise do with a malformed filter.
+    pub async fn subscribe<S: Into<String>>(
+        &self,
+        filter: S,
+        qos: QoS,
+    ) -> Result<(), ClientError> {
+        let filter = filter.into();
+        valid_filter(&filter)?;
+
+        let subscribe = Subscribe::new(filter, qos);
+        self.send(Request::Subscribe(subscribe)).await
+    }
+
+    /// Subscribes to many filters in a single request.
+    ///
+    /// Accepts anything convertible to [`SubscribeFilter`] — raw
+    /// `SubscribeFilter`s built from unvalidated strings (validated here,
+    /// the same as `subscribe`) or already-validated [`Filter`]s, which
+    /// carry their own QoS and are guaranteed to pass `valid_filter` since
+    /// that already ran at construction.
+    pub async fn subscribe_many<T, F>(&self, filters: T) -> Result<(), ClientError>
+    where
+        T: IntoIterator<Item = F>,
+        F: Into<SubscribeFilter>,
+    {
+        let filters: Vec<SubscribeFilter> = filters.into_iter().map(Into::into).collect();
+        for filter in &filters {
+            valid_filter(&filter.path)?;
+        }
+
+        self.send(Request::Subscribe(Subscribe::new_many(filters)))
+            .await
+    }
+}
+
+/// Lets `subscribe_many` take already-validated [`Filter`]s directly instead
+/// of the caller wrapping each one in a `SubscribeFilter` by hand. The QoS
+/// defaults to `QoS::AtMostOnce` if the `Filter` was never given one via
+/// `Filter::with_qos`.
+impl From<Filter> for SubscribeFilter {
+    fn from(filter: Filter) -> Self {
+        SubscribeFilter::new(
+            filter.as_str().to_string(),
+            filter.qos().unwrap_or(QoS::AtMostOnce),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AsyncClient, ClientError};
+    use crate::mqttbytes::topic::Filter;
+    use crate::mqttbytes::QoS;
+    use crate::{Request, SubscribeFilter};
+
+    fn test_client() -> (AsyncClient, tokio::sync::mpsc::Receiver<Request>) {
+        let (request_tx, request_rx) = tokio::sync::mpsc::channel(10);
+        (AsyncClient { request_tx }, request_rx)
+    }
+
+    #[tokio::test]
+    async fn publish_rejects_invalid_topics() {
+        let (client, _request_rx) = test_client();
+
+        let err = client
+            .publish("a/+", QoS::AtMostOnce, false, *b"")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::InvalidTopic(_)));
+    }
+
+    #[tokio::test]
+    async fn subscribe_rejects_invalid_filters() {
+        let (client, _request_rx) = test_client();
+
+        let err = client
+            .subscribe("a/++", QoS::AtMostOnce)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ClientError::InvalidFilter(_)));
+    }
+
+    #[tokio::test]
+    async fn subscribe_many_rejects_invalid_raw_filters() {
+        let (client, _request_rx) = test_client();
+
+        let filters = vec![SubscribeFilter::new("a/++".to_string(), QoS::AtMostOnce)];
+        let err = client.subscribe_many(filters).await.unwrap_err();
+        assert!(matches!(err, ClientError::InvalidFilter(_)));
+    }
+
+    #[tokio::test]
+    async fn subscribe_many_accepts_already_validated_filters() {
+        let (client, mut request_rx) = test_client();
+
+        let filters: Vec<Filter> = vec!["a/b".parse().unwrap(), "a/+/c".parse().unwrap()];
+        client.subscribe_many(filters).await.unwrap();
+
+        assert!(matches!(
+            request_rx.recv().await,
+            Some(Request::Subscribe(_))
+        ));
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+/// A trie that indexes many topic filters so a publish can be routed in
+/// O(topic-depth) instead of scanning every subscription's filter with
+/// [`matches`](super::topic::matches).
+///
+/// A node has a literal child per distinct level seen so far, plus two
+/// dedicated children for the `+` (single-level) and `#` (multi-level)
+/// wildcards. Values are attached at the node a filter terminates on; for a
+/// filter ending in `#` that's always the `#` child, since `#` must occupy
+/// the whole last level.
+#[derive(Debug)]
+pub struct SubscriptionTree<T> {
+    root: Node<T>,
+}
+
+#[derive(Debug)]
+struct Node<T> {
+    children: HashMap<String, Node<T>>,
+    plus: Option<Box<Node<T>>>,
+    hash: Option<Box<Node<T>>>,
+    values: Vec<T>,
+}
+
+impl<T> Default for Node<T> {
+    fn default() -> Self {
+        Node {
+            children: HashMap::new(),
+            plus: None,
+            hash: None,
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<T> Node<T> {
+    /// A node with no children, no wildcard children, and no values attached
+    /// is dead weight — nothing can ever match through it. [`remove`] prunes
+    /// these back up the path so steady subscribe/unsubscribe churn doesn't
+    /// leak nodes forever.
+    ///
+    /// [`remove`]: SubscriptionTree::remove
+    fn is_empty(&self) -> bool {
+        self.children.is_empty() && self.plus.is_none() && self.hash.is_none() && self.values.is_empty()
+    }
+}
+
+impl<T> Default for SubscriptionTree<T> {
+    fn default() -> Self {
+        SubscriptionTree {
+            root: Node::default(),
+        }
+    }
+}
+
+impl<T> SubscriptionTree<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indexes `value` under `filter`.
+    ///
+    /// `filter` isn't re-validated here; validate it with
+    /// [`valid_filter`](super::topic::valid_filter) at the API boundary first.
+    pub fn insert(&mut self, filter: &str, value: T) {
+        let mut node = &mut self.root;
+
+        for level in filter.split('/') {
+            node = match level {
+                "#" => &mut **node.hash.get_or_insert_with(Default::default),
+                "+" => &mut **node.plus.get_or_insert_with(Default::default),
+                _ => node.children.entry(level.to_string()).or_default(),
+            };
+
+            // "#" is only ever valid as the last level of a filter.
+            if level == "#" {
+                break;
+            }
+        }
+
+        node.values.push(value);
+    }
+
+    /// Removes the first value equal to `value` attached under `filter`, if
+    /// any. A no-op if `filter` was never inserted, or `value` isn't among
+    /// the values attached to it.
+    ///
+    /// Also prunes any node left empty (no children, no wildcard children,
+    /// no values) all the way back up to the root, so removal doesn't leak
+    /// nodes for filters nobody is subscribed to anymore.
+    pub fn remove(&mut self, filter: &str, value: &T)
+    where
+        T: PartialEq,
+    {
+        Self::remove_at(&mut self.root, filter.split('/'), value);
+    }
+
+    fn remove_at<'f>(node: &mut Node<T>, mut levels: impl Iterator<Item = &'f str>, value: &T)
+    where
+        T: PartialEq,
+    {
+        match levels.next() {
+            None => {
+                if let Some(pos) = node.values.iter().position(|v| v == value) {
+                    node.values.remove(pos);
+                }
+            }
+            // "#" is always terminal: there's nothing left to recurse into.
+            Some("#") => {
+                if let Some(child) = node.hash.as_mut() {
+                    if let Some(pos) = child.values.iter().position(|v| v == value) {
+                        child.values.remove(pos);
+                    }
+                    if child.is_empty() {
+                        node.hash = None;
+                    }
+                }
+            }
+            Some("+") => {
+                if let Some(child) = node.plus.as_mut() {
+                    Self::remove_at(child, levels, value);
+                    if child.is_empty() {
+                        node.plus = None;
+                    }
+                }
+            }
+            Some(level) => {
+                if let Some(child) = node.children.get_mut(level) {
+                    Self::remove_at(child, levels, value);
+                    if child.is_empty() {
+                        node.children.remove(level);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns every value attached to a filter that matches `topic`.
+    ///
+    /// Mirrors [`matches`](super::topic::matches)'s rule that a topic
+    /// beginning with `$` doesn't match anything here, the same way it
+    /// doesn't match any filter via `matches`.
+    pub fn matching(&self, topic: &str) -> impl Iterator<Item = &T> {
+        let mut out = Vec::new();
+
+        if topic.starts_with('$') {
+            return out.into_iter();
+        }
+
+        let levels: Vec<&str> = topic.split('/').collect();
+        Self::collect(&self.root, &levels, &mut out);
+        out.into_iter()
+    }
+
+    fn collect<'a>(node: &'a Node<T>, levels: &[&str], out: &mut Vec<&'a T>) {
+        // "#" matches the remainder of the topic, including zero levels, so
+        // its values are always collected regardless of what's left.
+        if let Some(hash) = &node.hash {
+            out.extend(hash.values.iter());
+        }
+
+        match levels.split_first() {
+            None => out.extend(node.values.iter()),
+            Some((level, rest)) => {
+                if let Some(child) = node.children.get(*level) {
+                    Self::collect(child, rest, out);
+                }
+
+                if let Some(plus) = &node.plus {
+                    Self::collect(plus, rest, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SubscriptionTree;
+    use crate::mqttbytes::topic::{matches, valid_filter};
+
+    #[test]
+    fn literal_filters_match_literal_topics() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/b/c", 1);
+        tree.insert("a/b/d", 2);
+
+        assert_eq!(tree.matching("a/b/c").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/b/d").collect::<Vec<_>>(), vec![&2]);
+        assert_eq!(tree.matching("a/b/e").collect::<Vec<_>>(), Vec::<&i32>::new());
+    }
+
+    #[test]
+    fn plus_matches_a_single_level() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/+/c", 1);
+
+        assert_eq!(tree.matching("a/b/c").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/x/c").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/b/c/d").collect::<Vec<_>>(), Vec::<&i32>::new());
+    }
+
+    #[test]
+    fn hash_matches_the_remainder_including_the_parent_level() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/b/#", 1);
+
+        assert_eq!(tree.matching("a/b").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/b/c").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/b/c/d").collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(tree.matching("a/x").collect::<Vec<_>>(), Vec::<&i32>::new());
+    }
+
+    #[test]
+    fn a_topic_can_match_several_filters() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/b/c", 1);
+        tree.insert("a/+/c", 2);
+        tree.insert("a/b/#", 3);
+        tree.insert("#", 4);
+
+        let mut matched = tree.matching("a/b/c").collect::<Vec<_>>();
+        matched.sort();
+        assert_eq!(matched, vec![&1, &2, &3, &4]);
+    }
+
+    #[test]
+    fn dollar_topics_match_nothing() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("$SYS/uptime", 1);
+        tree.insert("+/uptime", 2);
+        tree.insert("#", 3);
+
+        assert_eq!(
+            tree.matching("$SYS/uptime").collect::<Vec<_>>(),
+            Vec::<&i32>::new()
+        );
+    }
+
+    #[test]
+    fn matching_doesnt_panic_on_multi_byte_first_level() {
+        // The first level being a multi-byte UTF-8 character must not panic
+        // while checking for the leading '$' guard.
+        let mut tree = SubscriptionTree::new();
+        tree.insert("日本/x", 1);
+        tree.insert("+/x", 2);
+
+        let mut matched = tree.matching("日本/x").collect::<Vec<_>>();
+        matched.sort();
+        assert_eq!(matched, vec![&1, &2]);
+
+        assert_eq!(tree.matching("é/x").collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    fn remove_deletes_only_the_matching_value() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/b", 1);
+        tree.insert("a/b", 2);
+
+        tree.remove("a/b", &1);
+        assert_eq!(tree.matching("a/b").collect::<Vec<_>>(), vec![&2]);
+
+        // removing a filter that was never inserted, or a value that isn't
+        // attached to it, is a no-op
+        tree.remove("x/y", &2);
+        tree.remove("a/b", &42);
+        assert_eq!(tree.matching("a/b").collect::<Vec<_>>(), vec![&2]);
+    }
+
+    #[test]
+    fn remove_prunes_nodes_left_with_nothing_attached() {
+        let mut tree = SubscriptionTree::new();
+        tree.insert("a/b/+", 1);
+        tree.insert("a/b/#", 2);
+
+        tree.remove("a/b/+", &1);
+        // "a/b" still has the "#" child attached, so it must survive.
+        assert!(tree.root.children.contains_key("a"));
+        assert_eq!(tree.matching("a/b/c").collect::<Vec<_>>(), vec![&2]);
+
+        tree.remove("a/b/#", &2);
+        // nothing is attached anywhere under "a" anymore: the whole branch
+        // should have been pruned back to an empty root.
+        assert!(tree.root.children.is_empty());
+        assert!(tree.root.is_empty());
+    }
+
+    #[test]
+    fn agrees_with_matches_across_filters_and_topics() {
+        let filters = [
+            "a/b/c",
+            "a/+/c",
+            "a/b/#",
+            "+/+/+",
+            "#",
+            "a/b/c/d",
+            "sport/tennis/+",
+            "sport/#",
+        ];
+        let topics = [
+            "a/b/c",
+            "a/b/c/d",
+            "a/x/c",
+            "a/b",
+            "sport/tennis/player1",
+            "sport/tennis/player1/ranking",
+            "$SYS/uptime",
+        ];
+
+        let mut tree = SubscriptionTree::new();
+        for (i, filter) in filters.iter().enumerate() {
+            assert!(valid_filter(filter).is_ok());
+            tree.insert(filter, i);
+        }
+
+        for topic in topics {
+            let expected: Vec<usize> = filters
+                .iter()
+                .enumerate()
+                .filter(|(_, filter)| matches(topic, filter))
+                .map(|(i, _)| i)
+                .collect();
+
+            let mut actual: Vec<usize> = tree.matching(topic).copied().collect();
+            actual.sort();
+
+            assert_eq!(actual, expected, "mismatch for topic {topic:?}");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use super::QoS;
+
 #[derive(Debug)]
 pub enum InvalidTopicError {
     EmptyTopic,
@@ -6,6 +8,15 @@ pub enum InvalidTopicError {
     TooLong,
 }
 
+#[derive(Debug)]
+pub enum InvalidFilterError {
+    EmptyFilter,
+    ContainsNull,
+    TooLong,
+    MisplacedHash,
+    MisplacedPlus,
+}
+
 /// Checks if a topic or topic filter has wildcards
 pub fn has_wildcards(s: &str) -> bool {
     s.contains('+') || s.contains('#')
@@ -36,12 +47,60 @@ pub fn valid_topic(topic: &str) -> Result<(), InvalidTopicError> {
     Ok(())
 }
 
+/// Splits a shared-subscription filter of the form `$share/{ShareName}/{filter}`
+/// into its share name and effective filter.
+///
+/// Returns `None` if `filter` doesn't start with `$share/`, or if the shared
+/// subscription is malformed: an empty share name, a share name containing
+/// `/`, `+` or `#`, or a missing `{filter}` part.
+pub fn strip_shared_prefix(filter: &str) -> Option<(&str, &str)> {
+    let rest = filter.strip_prefix("$share/")?;
+    let (share_name, filter) = rest.split_once('/')?;
+
+    if share_name.is_empty() || share_name.contains(['/', '+', '#']) || filter.is_empty() {
+        return None;
+    }
+
+    Some((share_name, filter))
+}
+
 /// Checks if the filter is valid
 ///
 /// <https://docs.oasis-open.org/mqtt/mqtt/v3.1.1/os/mqtt-v3.1.1-os.html#_Toc398718106>
-pub fn valid_filter(filter: &str) -> bool {
+pub fn valid_filter(filter: &str) -> Result<(), InvalidFilterError> {
+    use InvalidFilterError::*;
+
+    // MQTT v5 shared subscriptions: `$share/{ShareName}/{filter}` is valid
+    // exactly when the share name is well formed and `{filter}` is a valid
+    // filter in its own right.
+    if filter.starts_with("$share/") || filter == "$share" {
+        if let Some((_share_name, filter)) = strip_shared_prefix(filter) {
+            return valid_filter(filter);
+        }
+
+        // strip_shared_prefix() rejected the share: diagnose why so callers
+        // get a meaningful error instead of a generic failure.
+        let rest = filter.strip_prefix("$share/").unwrap_or("");
+        let share_name = rest.split('/').next().unwrap_or("");
+        return if share_name.contains('#') {
+            Err(MisplacedHash)
+        } else if share_name.contains('+') {
+            Err(MisplacedPlus)
+        } else {
+            Err(EmptyFilter)
+        };
+    }
+
     if filter.is_empty() {
-        return false;
+        return Err(EmptyFilter);
+    }
+
+    if filter.contains('\0') {
+        return Err(ContainsNull);
+    }
+
+    if filter.len() > 65535 {
+        return Err(TooLong);
     }
 
     let hirerarchy = filter.split('/').collect::<Vec<&str>>();
@@ -51,25 +110,30 @@ pub fn valid_filter(filter: &str) -> bool {
             // invalid: sport/tennis#/player
             // invalid: sport/tennis/#/ranking
             if entry.contains('#') {
-                return false;
+                return Err(MisplacedHash);
             }
 
             // + must occupy an entire level of the filter
             // invalid: sport+
             if entry.len() > 1 && entry.contains('+') {
-                return false;
+                return Err(MisplacedPlus);
             }
         }
 
         // only single '#" or '+' is allowed in last entry
         // invalid: sport/tennis#
         // invalid: sport/++
-        if last.len() != 1 && (last.contains('#') || last.contains('+')) {
-            return false;
+        if last.len() != 1 {
+            if last.contains('#') {
+                return Err(MisplacedHash);
+            }
+            if last.contains('+') {
+                return Err(MisplacedPlus);
+            }
         }
     }
 
-    true
+    Ok(())
 }
 
 /// Checks if topic matches a filter. topic and filter validation isn't done here.
@@ -78,12 +142,28 @@ pub fn valid_filter(filter: &str) -> bool {
 /// **NOTE**: make sure a topic is validated during a publish and filter is validated
 /// during a subscribe
 pub fn matches(topic: &str, filter: &str) -> bool {
-    if !topic.is_empty() && topic[..1].contains('$') {
+    // A shared-subscription filter matches on its effective filter, i.e. the
+    // part following `$share/{ShareName}/`.
+    let filter = match strip_shared_prefix(filter) {
+        Some((_share_name, filter)) => filter,
+        None => filter,
+    };
+
+    if topic.starts_with('$') {
         return false;
     }
 
-    let mut topics = topic.split('/');
-    let mut filters = filter.split('/');
+    let topic_segments: Vec<&str> = topic.split('/').collect();
+    let filter_segments: Vec<&str> = filter.split('/').collect();
+    matches_segments(&topic_segments, &filter_segments)
+}
+
+/// Core of [`matches`], operating on already-split segments so callers that
+/// cache their segments (see [`Topic`] and [`Filter`]) don't pay to re-split
+/// the same string on every call.
+fn matches_segments<T: AsRef<str>, F: AsRef<str>>(topic: &[T], filter: &[F]) -> bool {
+    let mut topics = topic.iter().map(T::as_ref);
+    let mut filters = filter.iter().map(F::as_ref);
 
     for f in filters.by_ref() {
         // "#" being the last element is validated by the broker with 'valid_filter'
@@ -112,6 +192,172 @@ pub fn matches(topic: &str, filter: &str) -> bool {
     true
 }
 
+/// A validated MQTT topic name.
+///
+/// Construction (`FromStr`/`TryFrom<String>`) is the only way to get one, and
+/// it runs [`valid_topic`] once and caches the `/`-separated levels, so a
+/// `Topic` can be matched against many filters without re-splitting the
+/// string each time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Topic {
+    name: String,
+    segments: Vec<String>,
+}
+
+impl Topic {
+    /// The validated topic name.
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// Checks if this topic has wildcards. Always `false` for a `Topic`,
+    /// since [`valid_topic`] rejects `+` and `#`; kept for symmetry with
+    /// [`Filter::has_wildcards`].
+    pub fn has_wildcards(&self) -> bool {
+        has_wildcards(&self.name)
+    }
+
+    /// Checks if this topic matches `filter`, reusing both sides' cached
+    /// segments instead of re-splitting the underlying strings.
+    pub fn matches(&self, filter: &Filter) -> bool {
+        filter.matches(self)
+    }
+}
+
+impl std::str::FromStr for Topic {
+    type Err = InvalidTopicError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        name.to_string().try_into()
+    }
+}
+
+impl TryFrom<String> for Topic {
+    type Error = InvalidTopicError;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        valid_topic(&name)?;
+        let segments = name.split('/').map(String::from).collect();
+        Ok(Topic { name, segments })
+    }
+}
+
+impl std::fmt::Display for Topic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// A validated MQTT topic filter, optionally paired with the QoS it should be
+/// subscribed at.
+///
+/// Construction (`FromStr`/`TryFrom<String>`) runs [`valid_filter`] once and
+/// caches the effective (post `$share/{ShareName}/` stripping) `/`-separated
+/// levels, so a `Filter` can be matched against many topics without
+/// re-validating or re-splitting the pattern each time. Because it already
+/// carries its own QoS, `subscribe_many` can take validated `Filter`s
+/// directly instead of wrapping raw strings in a separate request type.
+///
+/// `Eq`/`Hash`/`PartialEq` key on `pattern` alone (which `share` and
+/// `segments` are derived from) and deliberately ignore `qos`: two `Filter`s
+/// for the same pattern are the same filter regardless of which QoS they're
+/// being subscribed at, and hand-rolling this avoids requiring `QoS: Hash`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    pattern: String,
+    share: Option<String>,
+    segments: Vec<String>,
+    qos: Option<QoS>,
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for Filter {}
+
+impl std::hash::Hash for Filter {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.pattern.hash(state);
+    }
+}
+
+impl Filter {
+    /// The validated filter pattern, including any `$share/{ShareName}/` prefix.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The share group name, if this is a shared-subscription filter.
+    pub fn share(&self) -> Option<&str> {
+        self.share.as_deref()
+    }
+
+    /// The QoS this filter should be subscribed at, if one has been set.
+    pub fn qos(&self) -> Option<QoS> {
+        self.qos
+    }
+
+    /// Returns this filter with its subscribe QoS set, for use with
+    /// `subscribe_many`.
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = Some(qos);
+        self
+    }
+
+    /// Checks if this filter has wildcards, reusing the cached segments
+    /// instead of re-scanning the pattern.
+    pub fn has_wildcards(&self) -> bool {
+        self.segments.iter().any(|s| s == "+" || s == "#")
+    }
+
+    /// Checks if `topic` matches this filter, reusing both sides' cached
+    /// segments instead of re-splitting the underlying strings.
+    pub fn matches(&self, topic: &Topic) -> bool {
+        if topic.segments.first().map_or(false, |s| s.starts_with('$')) {
+            return false;
+        }
+
+        matches_segments(&topic.segments, &self.segments)
+    }
+}
+
+impl std::str::FromStr for Filter {
+    type Err = InvalidFilterError;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        pattern.to_string().try_into()
+    }
+}
+
+impl TryFrom<String> for Filter {
+    type Error = InvalidFilterError;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        valid_filter(&pattern)?;
+
+        let (share, effective) = match strip_shared_prefix(&pattern) {
+            Some((share, effective)) => (Some(share.to_string()), effective.to_string()),
+            None => (None, pattern.clone()),
+        };
+
+        Ok(Filter {
+            segments: effective.split('/').map(String::from).collect(),
+            pattern,
+            share,
+            qos: None,
+        })
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -155,19 +401,192 @@ mod test {
 
     #[test]
     fn filters_are_validated_correctly() {
-        assert!(!super::valid_filter("wrong/#/filter"));
-        assert!(!super::valid_filter("wrong/wr#ng/filter"));
-        assert!(!super::valid_filter("wrong/filter#"));
-        assert!(super::valid_filter("correct/filter/#"));
-        assert!(!super::valid_filter("wr/o+/ng"));
-        assert!(!super::valid_filter("wr/+o+/ng"));
-        assert!(!super::valid_filter("wron/+g"));
-        assert!(super::valid_filter("cor/+/rect/+"));
+        use super::InvalidFilterError::*;
+
+        assert!(matches!(
+            super::valid_filter("wrong/#/filter"),
+            Err(MisplacedHash)
+        ));
+        assert!(matches!(
+            super::valid_filter("wrong/wr#ng/filter"),
+            Err(MisplacedHash)
+        ));
+        assert!(matches!(
+            super::valid_filter("wrong/filter#"),
+            Err(MisplacedHash)
+        ));
+        assert!(matches!(super::valid_filter("correct/filter/#"), Ok(())));
+        assert!(matches!(
+            super::valid_filter("wr/o+/ng"),
+            Err(MisplacedPlus)
+        ));
+        assert!(matches!(
+            super::valid_filter("wr/+o+/ng"),
+            Err(MisplacedPlus)
+        ));
+        assert!(matches!(
+            super::valid_filter("wron/+g"),
+            Err(MisplacedPlus)
+        ));
+        assert!(matches!(super::valid_filter("cor/+/rect/+"), Ok(())));
     }
 
     #[test]
     fn zero_len_subscriptions_are_not_allowed() {
-        assert!(!super::valid_filter(""));
+        assert!(matches!(
+            super::valid_filter(""),
+            Err(super::InvalidFilterError::EmptyFilter)
+        ));
+    }
+
+    #[test]
+    fn filters_enforce_null_and_length_limits() {
+        use super::InvalidFilterError::*;
+
+        assert!(matches!(
+            super::valid_filter("string_with_null\0"),
+            Err(ContainsNull)
+        ));
+        assert!(matches!(
+            super::valid_filter("string_with_no_null\\0"),
+            Ok(())
+        ));
+
+        let invalid_filter = String::from_utf8(vec![97; 65535 + 1]).unwrap();
+        assert!(matches!(super::valid_filter(&invalid_filter), Err(TooLong)));
+        let valid_filter = String::from_utf8(vec![97; 65535]).unwrap();
+        assert!(matches!(super::valid_filter(&valid_filter), Ok(())));
+    }
+
+    #[test]
+    fn shared_subscription_filters_are_validated_correctly() {
+        use super::InvalidFilterError::*;
+
+        assert!(matches!(super::valid_filter("$share/group/a/b"), Ok(())));
+        assert!(matches!(super::valid_filter("$share/group/+/b"), Ok(())));
+        assert!(matches!(super::valid_filter("$share/group/#"), Ok(())));
+
+        // no share name
+        assert!(matches!(
+            super::valid_filter("$share/"),
+            Err(EmptyFilter)
+        ));
+        assert!(matches!(
+            super::valid_filter("$share//a/b"),
+            Err(EmptyFilter)
+        ));
+        // no filter after the share name
+        assert!(matches!(
+            super::valid_filter("$share/group"),
+            Err(EmptyFilter)
+        ));
+        assert!(matches!(
+            super::valid_filter("$share/group/"),
+            Err(EmptyFilter)
+        ));
+        // share name with wildcards
+        assert!(matches!(
+            super::valid_filter("$share/gro+up/a/b"),
+            Err(MisplacedPlus)
+        ));
+        assert!(matches!(
+            super::valid_filter("$share/gro#up/a/b"),
+            Err(MisplacedHash)
+        ));
+        // the effective filter is still validated as a normal filter
+        assert!(matches!(
+            super::valid_filter("$share/group/a+/b"),
+            Err(MisplacedPlus)
+        ));
+    }
+
+    #[test]
+    fn filters_that_merely_start_with_share_are_plain_literal_filters() {
+        // "$share" is only special as the exact first level; a filter that
+        // just happens to start with those six characters is a normal,
+        // valid filter and must not be routed into the shared-subscription
+        // parsing path.
+        assert!(matches!(
+            super::valid_filter("$shareholder/x"),
+            Ok(())
+        ));
+        assert!(matches!(
+            super::valid_filter("$share-data/topic"),
+            Ok(())
+        ));
+        assert!(matches!(super::valid_filter("$sharexyz"), Ok(())));
+    }
+
+    #[test]
+    fn strips_shared_subscription_prefix() {
+        assert_eq!(
+            super::strip_shared_prefix("$share/group/a/b"),
+            Some(("group", "a/b"))
+        );
+        assert_eq!(super::strip_shared_prefix("$share/group"), None);
+        assert_eq!(super::strip_shared_prefix("$share/gro+up/a/b"), None);
+        assert_eq!(super::strip_shared_prefix("a/b/c"), None);
+    }
+
+    #[test]
+    fn shared_subscriptions_match_on_the_effective_filter() {
+        assert!(super::matches("a/b", "$share/group/a/b"));
+        assert!(super::matches("a/b", "$share/group/a/+"));
+        assert!(super::matches("a/b/c", "$share/group/a/#"));
+        assert!(!super::matches("a/b", "$share/group/a/c"));
+
+        // the '$' guard on the topic still applies to the effective filter
+        assert!(!super::matches("$system/metrics", "$share/group/+/metrics"));
+    }
+
+    #[test]
+    fn topic_newtype_validates_and_displays() {
+        use super::Topic;
+
+        let topic: Topic = "a/b/c".parse().unwrap();
+        assert_eq!(topic.as_str(), "a/b/c");
+        assert_eq!(topic.to_string(), "a/b/c");
+        assert!(!topic.has_wildcards());
+
+        assert!("a/+/c".parse::<Topic>().is_err());
+        assert!("".parse::<Topic>().is_err());
+    }
+
+    #[test]
+    fn filter_newtype_validates_share_and_qos() {
+        use super::{Filter, QoS};
+
+        let filter: Filter = "$share/group/a/+".parse().unwrap();
+        assert_eq!(filter.as_str(), "$share/group/a/+");
+        assert_eq!(filter.share(), Some("group"));
+        assert!(filter.has_wildcards());
+        assert_eq!(filter.qos(), None);
+
+        let filter = filter.with_qos(QoS::AtLeastOnce);
+        assert_eq!(filter.qos(), Some(QoS::AtLeastOnce));
+
+        let plain: Filter = "a/b/+".parse().unwrap();
+        assert_eq!(plain.share(), None);
+
+        assert!("a/++".parse::<Filter>().is_err());
+    }
+
+    #[test]
+    fn newtypes_match_using_cached_segments() {
+        use super::{Filter, Topic};
+
+        let topic: Topic = "a/b/c".parse().unwrap();
+        let filter: Filter = "a/+/c".parse().unwrap();
+        assert!(topic.matches(&filter));
+        assert!(filter.matches(&topic));
+
+        let shared_filter: Filter = "$share/group/a/+/c".parse().unwrap();
+        let topic: Topic = "a/x/c".parse().unwrap();
+        assert!(shared_filter.matches(&topic));
+
+        let dollar_topic: Topic = "$SYS/stats".parse().unwrap();
+        let wildcard_filter: Filter = "+/stats".parse().unwrap();
+        assert!(!wildcard_filter.matches(&dollar_topic));
     }
 
     #[test]
@@ -177,6 +596,15 @@ mod test {
         assert!(!super::matches("$system/metrics", "+/+"));
     }
 
+    #[test]
+    fn matches_doesnt_panic_on_multi_byte_first_level() {
+        // The '$' guard used to byte-slice the first character, which
+        // panicked whenever the topic's first level was multi-byte UTF-8.
+        assert!(super::matches("日本/x", "+/x"));
+        assert!(super::matches("日本/x", "日本/x"));
+        assert!(super::matches("é/x", "#"));
+    }
+
     #[test]
     fn topics_match_with_filters_as_expected() {
         let topic = "a/b/c";